@@ -1,15 +1,32 @@
 use anyhow::{Context, Result};
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, ToSocketAddrs};
+
+/// Preferência de família de endereço ao resolver um hostname (`-4`/`-6`).
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AddrPref {
+    Any,
+    V4,
+    V6,
+}
 
 pub struct PingArgs {
-    pub dst: Ipv4Addr,
+    /// Argumento original informado pelo usuário (IP ou hostname), usado no banner.
+    pub host: String,
+    pub dst: IpAddr,
     pub count: Option<u64>,
+    /// Tamanho em bytes do payload do Echo Request (`-s`), incluindo o timestamp embutido.
+    pub payload_size: usize,
+    /// TTL (IPv4) / hop limit (IPv6) a definir no socket (`-t`); `None` usa o padrão do SO.
+    pub ttl: Option<u32>,
 }
 
 pub fn parse() -> Result<PingArgs> {
     let args: Vec<String> = std::env::args().collect();
     let mut dst_str = None;
     let mut count = None;
+    let mut af_pref = AddrPref::Any;
+    let mut payload_size = crate::DEFAULT_PAYLOAD_SIZE;
+    let mut ttl = None;
 
     let mut i = 1;
     while i < args.len() {
@@ -23,17 +40,74 @@ pub fn parse() -> Result<PingArgs> {
                     anyhow::bail!("Faltou o valor para -c");
                 }
             }
+            "-s" => {
+                if i + 1 < args.len() {
+                    let s: usize = args[i + 1].parse().context("Valor inválido para -s")?;
+                    if s > crate::MAX_PAYLOAD_SIZE {
+                        anyhow::bail!(
+                            "Valor para -s não pode passar de {} bytes (resposta não caberia no buffer de recepção)",
+                            crate::MAX_PAYLOAD_SIZE
+                        );
+                    }
+                    payload_size = s;
+                    i += 1;
+                } else {
+                    anyhow::bail!("Faltou o valor para -s");
+                }
+            }
+            "-t" => {
+                if i + 1 < args.len() {
+                    let t: u32 = args[i + 1].parse().context("Valor inválido para -t")?;
+                    ttl = Some(t);
+                    i += 1;
+                } else {
+                    anyhow::bail!("Faltou o valor para -t");
+                }
+            }
+            "-4" => af_pref = AddrPref::V4,
+            "-6" => af_pref = AddrPref::V6,
             val => {
                 if dst_str.is_none() {
-                    dst_str = Some(val);
+                    dst_str = Some(val.to_string());
                 }
             }
         }
         i += 1;
     }
 
-    let dst_str = dst_str.context("Uso: pingrs <ipv4> [-c <count>]")?;
-    let dst: Ipv4Addr = dst_str.parse().context("Endereço IP inválido")?;
+    let host = dst_str.context("Uso: pingrs <host|ip> [-c <count>] [-s <size>] [-t <ttl>] [-4|-6]")?;
+    let dst = resolve(&host, af_pref)?;
+
+    Ok(PingArgs {
+        host,
+        dst,
+        count,
+        payload_size,
+        ttl,
+    })
+}
+
+/// Resolve `host` para um `IpAddr`, tentando primeiro um parse direto (já é um
+/// literal IPv4/IPv6) e, caso contrário, um lookup de DNS via `ToSocketAddrs`,
+/// filtrado pela família preferida (`-4`/`-6`).
+fn resolve(host: &str, pref: AddrPref) -> Result<IpAddr> {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(ip);
+    }
+
+    let candidates: Vec<IpAddr> = (host, 0)
+        .to_socket_addrs()
+        .with_context(|| format!("Falha ao resolver host '{}'", host))?
+        .map(|sa| sa.ip())
+        .collect();
+
+    let chosen = match pref {
+        AddrPref::V4 => candidates.iter().find(|ip| ip.is_ipv4()),
+        AddrPref::V6 => candidates.iter().find(|ip| ip.is_ipv6()),
+        AddrPref::Any => candidates.first(),
+    };
 
-    Ok(PingArgs { dst, count })
+    chosen
+        .copied()
+        .with_context(|| format!("Nenhum endereço resolvido para '{}'", host))
 }
@@ -1,4 +1,6 @@
-/// Calcula o checksum ICMP (RFC 792).
+use std::net::Ipv6Addr;
+
+/// Calcula o checksum ICMP (RFC 792), também usado para somar o pseudo-header do ICMPv6.
 fn checksum(mut data: &[u8]) -> u16 {
     let mut sum: u32 = 0;
     while data.len() >= 2 {
@@ -36,3 +38,198 @@ pub fn build_echo_request(ident: u16, seq: u16, payload: &[u8]) -> Vec<u8> {
 
     pkt
 }
+
+/// Monta um pacote ICMPv6 Echo Request (type=128, code=0).
+///
+/// Ao contrário do ICMPv4, o checksum do ICMPv6 é calculado sobre um pseudo-header
+/// (RFC 8200, seção 8.1): endereço de origem, endereço de destino, tamanho da
+/// mensagem ICMPv6 e o next-header 58, seguido da própria mensagem. Por isso
+/// `src` precisa ser o endereço local escolhido pelo SO para alcançar `dst`.
+pub fn build_echo_request_v6(
+    ident: u16,
+    seq: u16,
+    payload: &[u8],
+    src: Ipv6Addr,
+    dst: Ipv6Addr,
+) -> Vec<u8> {
+    // Mensagem ICMPv6 (8 bytes de cabeçalho) + payload
+    let mut msg = Vec::with_capacity(8 + payload.len());
+
+    // Type=128 (Echo Request), Code=0, checksum placeholder (2 bytes)
+    msg.extend_from_slice(&[128, 0, 0, 0]);
+
+    // Identifier e Sequence (big-endian)
+    msg.extend_from_slice(&ident.to_be_bytes());
+    msg.extend_from_slice(&seq.to_be_bytes());
+
+    // Payload arbitrário (timestamp, texto, etc.)
+    msg.extend_from_slice(payload);
+
+    // Pseudo-header: origem (16) + destino (16) + tamanho (4) + zeros (3) + next-header (1)
+    let mut pseudo = Vec::with_capacity(40 + msg.len());
+    pseudo.extend_from_slice(&src.octets());
+    pseudo.extend_from_slice(&dst.octets());
+    pseudo.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+    pseudo.extend_from_slice(&[0, 0, 0, 58]);
+    pseudo.extend_from_slice(&msg);
+
+    let csum = checksum(&pseudo);
+    msg[2] = (csum >> 8) as u8;
+    msg[3] = (csum & 0xFF) as u8;
+
+    msg
+}
+
+/// Categoria de uma mensagem de erro ICMP recebida no lugar de um Echo Reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IcmpErrorKind {
+    DestinationUnreachable,
+    TimeExceeded,
+    Other(u8, u8),
+}
+
+impl std::fmt::Display for IcmpErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IcmpErrorKind::DestinationUnreachable => write!(f, "Host de destino inacessível"),
+            IcmpErrorKind::TimeExceeded => write!(f, "TTL excedido em trânsito"),
+            IcmpErrorKind::Other(t, c) => write!(f, "Erro ICMP (type={}, code={})", t, c),
+        }
+    }
+}
+
+/// Tenta interpretar `icmp` como uma mensagem de erro ICMPv4 (Destination
+/// Unreachable, Time Exceeded, etc). Essas mensagens embutem o cabeçalho IP
+/// original e os primeiros 8 bytes do datagrama original logo após seus
+/// próprios 8 bytes de cabeçalho; dali recuperamos o identifier/sequence do
+/// Echo Request que provocou o erro. Retorna `None` se não for uma mensagem
+/// de erro reconhecida ou se o cabeçalho embutido estiver incompleto.
+pub fn parse_error_v4(icmp: &[u8]) -> Option<(IcmpErrorKind, u16, u16)> {
+    if icmp.len() < 8 + 20 {
+        return None;
+    }
+
+    let kind = match icmp[0] {
+        3 => IcmpErrorKind::DestinationUnreachable,
+        11 => IcmpErrorKind::TimeExceeded,
+        t @ (4 | 5) => IcmpErrorKind::Other(t, icmp[1]),
+        _ => return None,
+    };
+
+    let orig_ip = &icmp[8..];
+    let ihl = (orig_ip[0] & 0x0F) as usize * 4;
+    if orig_ip.len() < ihl + 8 {
+        return None;
+    }
+    let orig_icmp = &orig_ip[ihl..];
+    let orig_id = u16::from_be_bytes([orig_icmp[4], orig_icmp[5]]);
+    let orig_seq = u16::from_be_bytes([orig_icmp[6], orig_icmp[7]]);
+
+    Some((kind, orig_id, orig_seq))
+}
+
+/// Equivalente a [`parse_error_v4`] para ICMPv6 (Destination Unreachable
+/// type=1, Time Exceeded type=3). O cabeçalho IPv6 original embutido tem
+/// tamanho fixo de 40 bytes (sem cabeçalhos de extensão).
+pub fn parse_error_v6(icmp: &[u8]) -> Option<(IcmpErrorKind, u16, u16)> {
+    const IPV6_HEADER_LEN: usize = 40;
+    if icmp.len() < 8 + IPV6_HEADER_LEN + 8 {
+        return None;
+    }
+
+    let kind = match icmp[0] {
+        1 => IcmpErrorKind::DestinationUnreachable,
+        3 => IcmpErrorKind::TimeExceeded,
+        _ => return None,
+    };
+
+    let orig_icmp = &icmp[8 + IPV6_HEADER_LEN..];
+    let orig_id = u16::from_be_bytes([orig_icmp[4], orig_icmp[5]]);
+    let orig_seq = u16::from_be_bytes([orig_icmp[6], orig_icmp[7]]);
+
+    Some((kind, orig_id, orig_seq))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_fecha_em_zero_apos_embutido_no_pacote() {
+        // Propriedade clássica do checksum da Internet (RFC 1071): somar um
+        // buffer que já contém seu próprio checksum fecha em zero.
+        let pkt = build_echo_request(0x1234, 42, b"abc");
+        assert_eq!(checksum(&pkt), 0);
+    }
+
+    #[test]
+    fn echo_request_v4_tem_type_code_ident_seq_corretos() {
+        let pkt = build_echo_request(0xBEEF, 7, b"payload-teste");
+        assert_eq!(pkt[0], 8); // type = Echo Request
+        assert_eq!(pkt[1], 0); // code
+        assert_eq!(u16::from_be_bytes([pkt[4], pkt[5]]), 0xBEEF);
+        assert_eq!(u16::from_be_bytes([pkt[6], pkt[7]]), 7);
+        assert_eq!(&pkt[8..], b"payload-teste");
+    }
+
+    #[test]
+    fn echo_request_v6_usa_pseudo_header_no_checksum() {
+        let src: Ipv6Addr = "fe80::1".parse().unwrap();
+        let dst: Ipv6Addr = "fe80::2".parse().unwrap();
+        let msg = build_echo_request_v6(0x1234, 1, b"oi", src, dst);
+
+        assert_eq!(msg[0], 128); // type = Echo Request (v6)
+        assert_eq!(msg[1], 0);
+
+        // Recria o pseudo-header (RFC 8200 §8.1) manualmente: somado à mensagem
+        // já com o checksum embutido, deve fechar em zero.
+        let mut pseudo = Vec::new();
+        pseudo.extend_from_slice(&src.octets());
+        pseudo.extend_from_slice(&dst.octets());
+        pseudo.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+        pseudo.extend_from_slice(&[0, 0, 0, 58]);
+        pseudo.extend_from_slice(&msg);
+        assert_eq!(checksum(&pseudo), 0);
+
+        // Mudar o endereço de origem muda o pseudo-header e, portanto, o checksum
+        // resultante — prova de que ele de fato participa do cálculo.
+        let outro_src: Ipv6Addr = "fe80::99".parse().unwrap();
+        let msg2 = build_echo_request_v6(0x1234, 1, b"oi", outro_src, dst);
+        assert_ne!(&msg[2..4], &msg2[2..4]);
+    }
+
+    #[test]
+    fn parse_error_v4_recupera_id_e_seq_do_echo_request_original() {
+        // Erro ICMPv4 (Time Exceeded) embutindo um cabeçalho IP original sem
+        // opções (IHL=5) seguido dos 8 bytes do Echo Request que o provocou.
+        let mut icmp = vec![11, 0, 0, 0, 0, 0, 0, 0]; // type=11, code=0
+        let mut orig_ip = vec![0x45u8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        orig_ip.extend_from_slice(&[8, 0, 0, 0, 0x12, 0x34, 0, 7]); // type=8, code=0, ident=0x1234, seq=7
+        icmp.extend_from_slice(&orig_ip);
+
+        let (kind, ident, seq) =
+            parse_error_v4(&icmp).expect("deveria reconhecer a mensagem de erro");
+        assert_eq!(kind, IcmpErrorKind::TimeExceeded);
+        assert_eq!(ident, 0x1234);
+        assert_eq!(seq, 7);
+    }
+
+    #[test]
+    fn parse_error_v4_rejeita_cabecalho_embutido_incompleto() {
+        let icmp = vec![3, 1, 0, 0, 0, 0, 0, 0]; // sem cabeçalho IP original embutido
+        assert!(parse_error_v4(&icmp).is_none());
+    }
+
+    #[test]
+    fn parse_error_v6_recupera_id_e_seq_do_echo_request_original() {
+        let mut icmp = vec![3, 0, 0, 0, 0, 0, 0, 0]; // type=3 (Time Exceeded), code=0
+        icmp.extend(std::iter::repeat(0u8).take(40)); // cabeçalho IPv6 original (fixo, sem ext headers)
+        icmp.extend_from_slice(&[128, 0, 0, 0, 0x43, 0x21, 0, 9]); // ident=0x4321, seq=9
+
+        let (kind, ident, seq) =
+            parse_error_v6(&icmp).expect("deveria reconhecer a mensagem de erro");
+        assert_eq!(kind, IcmpErrorKind::TimeExceeded);
+        assert_eq!(ident, 0x4321);
+        assert_eq!(seq, 9);
+    }
+}
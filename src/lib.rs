@@ -0,0 +1,526 @@
+//! Motor ICMP do `pingrs`: envia Echo Requests (ICMPv4/ICMPv6) e interpreta
+//! as respostas. O binário `pingrs` é apenas uma CLI fina sobre o [`Pinger`]
+//! exposto aqui; o mesmo motor pode ser embutido em outras ferramentas
+//! (monitoramento, testes) sem passar pelo processo `main`.
+
+pub mod args;
+pub mod icmp;
+
+use anyhow::{Context, Result};
+use polling::{Event, Events, Poller};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Aguarda até que o socket registrado em `poller` fique pronto para leitura ou `timeout` se esgote,
+/// sem girar a CPU: o `Poller` bloqueia a thread em `poll`/`epoll`/`kqueue`
+/// (conforme o SO) até haver dado disponível ou o timeout vencer.
+fn wait_readable(poller: &Poller, events: &mut Events, timeout: Duration) -> io::Result<bool> {
+    events.clear();
+    let ready = poller.wait(events, Some(timeout))?;
+    Ok(ready > 0)
+}
+
+/// Habilita a entrega do hop limit IPv6 como ancillary data (`cmsg`) em cada
+/// `recvmsg`, via o socket option `IPV6_RECVHOPLIMIT`. Sem isso o kernel não
+/// inclui essa informação, pois sockets raw ICMPv6 não trazem o cabeçalho IP.
+#[cfg(unix)]
+fn enable_recv_hop_limit_v6(sock: &Socket) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let enable: libc::c_int = 1;
+    // SAFETY: `enable` vive até o fim da chamada e seu tamanho é o informado.
+    let ret = unsafe {
+        libc::setsockopt(
+            sock.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::IPV6_RECVHOPLIMIT,
+            &enable as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Lê um datagrama ICMPv6 via `recvmsg`, recuperando tanto o endereço do
+/// remetente quanto o hop limit entregue como ancillary data (ver
+/// [`enable_recv_hop_limit_v6`]). `socket2::Socket::recv_from` não expõe
+/// ancillary data, por isso usamos `libc::recvmsg` diretamente aqui.
+///
+/// Retorna `from = None` se o kernel não preencher `msg_name` (não deveria
+/// acontecer em um socket não conectado, mas não confiamos cegamente nisso);
+/// quem chama decide o fallback, como já faz para o caminho IPv4.
+#[cfg(unix)]
+fn recv_from_v6_with_hop_limit(
+    sock: &Socket,
+    buf: &mut [MaybeUninit<u8>],
+) -> io::Result<(usize, Option<IpAddr>, Option<u8>)> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut name: libc::sockaddr_in6 = unsafe { std::mem::zeroed() };
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    // Espaço suficiente para um cmsg com um `c_int` (o hop limit).
+    let mut cmsg_buf = [0u8; 64];
+
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_name = &mut name as *mut libc::sockaddr_in6 as *mut libc::c_void;
+    msg.msg_namelen = std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = cmsg_buf.len() as _;
+
+    // SAFETY: `msg` aponta para buffers válidos (`buf`, `name`, `cmsg_buf`)
+    // que vivem até o fim desta chamada.
+    let n = unsafe { libc::recvmsg(sock.as_raw_fd(), &mut msg, 0) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // `msg_namelen` só é confiável depois do `recvmsg`: se o kernel não
+    // escreveu um `sockaddr_in6` completo em `name`, não devemos ler
+    // `sin6_addr` como se fosse válido.
+    let from = if msg.msg_namelen as usize >= std::mem::size_of::<libc::sockaddr_in6>() {
+        Some(IpAddr::V6(Ipv6Addr::from(name.sin6_addr.s6_addr)))
+    } else {
+        None
+    };
+
+    let mut hop_limit = None;
+    // SAFETY: `msg` foi preenchido pelo `recvmsg` acima; `CMSG_FIRSTHDR`/
+    // `CMSG_NXTHDR`/`CMSG_DATA` são as formas padrão de percorrer seu ancillary data.
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let hdr = &*cmsg;
+            if hdr.cmsg_level == libc::IPPROTO_IPV6 && hdr.cmsg_type == libc::IPV6_HOPLIMIT {
+                let data = libc::CMSG_DATA(cmsg) as *const libc::c_int;
+                hop_limit = Some(*data as u8);
+                break;
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    Ok((n as usize, from, hop_limit))
+}
+
+/// Tamanho padrão do payload, igual ao antigo `b"pingrs-windows"` fixo.
+pub const DEFAULT_PAYLOAD_SIZE: usize = 14;
+
+/// Tamanho mínimo do payload: precisa caber o timestamp de 8 bytes embutido.
+const MIN_PAYLOAD_SIZE: usize = 8;
+
+/// Tamanho do buffer de recepção (MTU típica).
+const RECV_BUF_SIZE: usize = 1500;
+
+/// Maior payload aceito em `-s`: o suficiente para que o Echo Reply (cabeçalho
+/// ICMP de 8 bytes, mais até 60 bytes de cabeçalho IPv4 com opções, no pior
+/// caso) nunca ultrapasse [`RECV_BUF_SIZE`]. Acima disso a resposta seria
+/// truncada no `recv` e o payload ecoado pareceria corrompido mesmo sem ter
+/// sido.
+pub const MAX_PAYLOAD_SIZE: usize = RECV_BUF_SIZE - 60 - 8;
+
+/// Padrão de preenchimento usado após o timestamp, igual ao do `ping` do iputils.
+const PAYLOAD_FILLER: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWX";
+
+/// Monta o payload de um Echo Request: os primeiros 8 bytes são um timestamp
+/// monotônico (nanossegundos desde a criação do `Pinger`), usado para
+/// calcular o RTT a partir da própria resposta; o restante é preenchido com
+/// um padrão reconhecível, usado para detectar corrupção na resposta.
+fn build_payload(size: usize, timestamp_nanos: u64) -> Vec<u8> {
+    let size = size.max(MIN_PAYLOAD_SIZE);
+    let mut payload = Vec::with_capacity(size);
+    payload.extend_from_slice(&timestamp_nanos.to_be_bytes());
+    for i in 0..size - MIN_PAYLOAD_SIZE {
+        payload.push(PAYLOAD_FILLER[i % PAYLOAD_FILLER.len()]);
+    }
+    payload
+}
+
+/// Opções de configuração de um [`Pinger`].
+pub struct PingerOpts {
+    /// Número de Echo Requests a enviar; `None` pinga indefinidamente.
+    pub count: Option<u64>,
+    /// Intervalo entre o início de um envio e o início do próximo.
+    pub interval: Duration,
+    /// Prazo de espera por uma resposta a cada Echo Request.
+    pub timeout: Duration,
+    /// Tamanho em bytes do payload do Echo Request, incluindo o timestamp embutido.
+    pub payload_size: usize,
+    /// TTL (IPv4) / hop limit (IPv6) a definir no socket; `None` usa o padrão do SO.
+    pub ttl: Option<u32>,
+    /// Sinalizador de interrupção (Ctrl+C); quando `false`, o `Pinger` encerra
+    /// a iteração assim que possível.
+    pub running: Arc<AtomicBool>,
+}
+
+impl Default for PingerOpts {
+    fn default() -> Self {
+        Self {
+            count: None,
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(2),
+            payload_size: DEFAULT_PAYLOAD_SIZE,
+            ttl: None,
+            running: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+/// Resultado de uma tentativa de ping.
+pub enum PingOutcome {
+    /// Echo Reply recebido e correspondente ao nosso pedido.
+    Reply {
+        seq: u16,
+        rtt: Duration,
+        bytes: usize,
+        /// TTL (IPv4) / hop limit (IPv6) com que a resposta chegou, quando disponível.
+        ttl: Option<u8>,
+        /// `true` se o payload ecoado não bate com o que foi enviado.
+        corrupted: bool,
+    },
+    /// Nenhuma resposta chegou dentro do prazo (ou fomos interrompidos).
+    Timeout { seq: u16 },
+    /// Uma mensagem de erro ICMP (inacessível, TTL excedido, ...) chegou no
+    /// lugar de um Echo Reply.
+    Error {
+        seq: u16,
+        kind: icmp::IcmpErrorKind,
+        /// Endereço de quem respondeu o erro (o hop intermediário, no caso de
+        /// um TTL excedido) — não necessariamente `dst`.
+        from: IpAddr,
+    },
+}
+
+/// Motor de ping: mantém o socket RAW, o identificador, o payload e o estado
+/// de sequência, e produz um [`PingOutcome`] por Echo Request via
+/// [`Pinger::ping_once`] ou pelo `Iterator` que ele implementa.
+pub struct Pinger {
+    sock: Socket,
+    poller: Poller,
+    events: Events,
+    dst: IpAddr,
+    dst_sa: SocketAddr,
+    src_v6: Option<Ipv6Addr>,
+    ident: u16,
+    seq: u16,
+    sent: u64,
+    /// Época dos timestamps embutidos no payload (nanossegundos desde a criação do `Pinger`).
+    start: Instant,
+    opts: PingerOpts,
+}
+
+impl Pinger {
+    /// Cria um socket ICMP RAW para `dst` (IPv4 ou IPv6) e o prepara para
+    /// enviar Echo Requests conforme `opts`.
+    pub fn new(dst: IpAddr, opts: PingerOpts) -> Result<Self> {
+        // Domain::IPV4/IPV6 -> AF_INET/AF_INET6, conforme a família do destino
+        // Type::RAW -> SOCK_RAW (Necessário no Windows para ICMP)
+        // Protocol::ICMPV4/ICMPV6 -> IPPROTO_ICMP/IPPROTO_ICMPV6
+        // Nota: SOCK_RAW é 3. Usamos o valor direto pois libc::SOCK_RAW pode não estar disponível no Windows.
+        let (domain, protocol) = match dst {
+            IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4),
+            IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6),
+        };
+        let mut sock = Socket::new(domain, Type::from(3), Some(protocol))
+            .context("Falha ao criar socket RAW. Verifique se está rodando como Administrador.")?;
+
+        // Non-blocking: a espera por dados é feita pelo Poller, não por um timeout de leitura
+        sock.set_nonblocking(true)?;
+
+        // TTL (IPv4) / hop limit (IPv6) customizado via `-t`, base para um futuro modo traceroute
+        if let Some(ttl) = opts.ttl {
+            match dst {
+                IpAddr::V4(_) => sock.set_ttl(ttl).context("Falha ao definir o TTL")?,
+                IpAddr::V6(_) => sock
+                    .set_unicast_hops_v6(ttl)
+                    .context("Falha ao definir o hop limit")?,
+            }
+        }
+
+        // Sockets raw ICMPv6 não incluem o cabeçalho IP na resposta (diferente do
+        // IPv4), então o hop limit com que ela chegou só é visível como ancillary
+        // data; pedimos ao kernel para entregá-lo junto de cada `recvmsg`.
+        #[cfg(unix)]
+        if let IpAddr::V6(_) = dst {
+            enable_recv_hop_limit_v6(&sock).context("Falha ao habilitar IPV6_RECVHOPLIMIT")?;
+        }
+
+        let poller = Poller::new().context("Falha ao criar o poller de leitura")?;
+        // SAFETY: `sock` é um campo de `Pinger` e só é descartado junto com `poller`,
+        // que o remove implicitamente ao ser dropado.
+        unsafe {
+            poller
+                .add(&sock, Event::readable(0))
+                .context("Falha ao registrar o socket no poller")?;
+        }
+        let events = Events::new();
+
+        // Endereço de destino (porta 0 é ignorada para ICMP)
+        let dst_sa = SocketAddr::new(dst, 0);
+
+        // No caminho IPv6 o checksum do ICMPv6 depende do endereço de origem
+        // (pseudo-header). Descobrimos qual endereço local o SO escolheria
+        // conectando um socket UDP descartável — e não o socket raw, pois um
+        // socket raw conectado só entrega pacotes cujo remetente seja o par
+        // conectado, descartando silenciosamente erros ICMP (inacessível, TTL
+        // excedido) vindos de hops intermediários, que não são `dst`.
+        let src_v6: Option<Ipv6Addr> = if let IpAddr::V6(_) = dst {
+            let probe = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))
+                .context("Falha ao criar socket auxiliar para descobrir o endereço de origem IPv6")?;
+            probe
+                .connect(&dst_sa.into())
+                .context("Falha ao resolver endereço de origem IPv6")?;
+            match probe.local_addr()?.as_socket() {
+                Some(SocketAddr::V6(local)) => Some(*local.ip()),
+                _ => anyhow::bail!("Não foi possível determinar o endereço IPv6 local"),
+            }
+        } else {
+            None
+        };
+
+        // Identificador: usa o PID do processo (comum em pings)
+        let ident: u16 = std::process::id() as u16;
+
+        Ok(Self {
+            sock,
+            poller,
+            events,
+            dst,
+            dst_sa,
+            src_v6,
+            ident,
+            seq: 1,
+            sent: 0,
+            start: Instant::now(),
+            opts,
+        })
+    }
+
+    /// Tamanho em bytes do payload enviado em cada Echo Request.
+    pub fn payload_len(&self) -> usize {
+        self.opts.payload_size.max(MIN_PAYLOAD_SIZE)
+    }
+
+    /// Envia um único Echo Request e aguarda a resposta correspondente (ou o
+    /// prazo, ou um erro ICMP), avançando o número de sequência interno.
+    pub fn ping_once(&mut self) -> Result<PingOutcome> {
+        let seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        if self.seq == 0 {
+            self.seq = 1;
+        }
+
+        let t0 = Instant::now();
+        let timestamp_nanos = t0.duration_since(self.start).as_nanos() as u64;
+        let payload = build_payload(self.opts.payload_size, timestamp_nanos);
+
+        let pkt = match self.dst {
+            IpAddr::V4(_) => icmp::build_echo_request(self.ident, seq, &payload),
+            IpAddr::V6(v6_dst) => {
+                let src = self
+                    .src_v6
+                    .expect("endereço de origem IPv6 deveria ter sido resolvido");
+                icmp::build_echo_request_v6(self.ident, seq, &payload, src, v6_dst)
+            }
+        };
+
+        self.sent += 1;
+
+        // O socket raw (v4 e v6) nunca é conectado — ver comentário em `Pinger::new`
+        // sobre por que o endereço de origem IPv6 é descoberto por um socket à parte —,
+        // então `send_to` funciona igual para as duas famílias.
+        self.sock
+            .send_to(&pkt, &self.dst_sa.into())
+            .context("Falha ao enviar Echo Request")?;
+
+        // Buffer de recepção; payload_size é validado em args::parse (MAX_PAYLOAD_SIZE)
+        // para que o Echo Reply correspondente sempre caiba aqui sem truncar.
+        let mut buf = [MaybeUninit::uninit(); RECV_BUF_SIZE];
+        let deadline = t0 + self.opts.timeout;
+
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(PingOutcome::Timeout { seq });
+            }
+            if !self.opts.running.load(Ordering::SeqCst) {
+                return Ok(PingOutcome::Timeout { seq });
+            }
+
+            // Bloqueia até haver dado para ler ou faltar o prazo do pedido, sem busy-wait
+            match wait_readable(&self.poller, &mut self.events, deadline - now) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => return Err(e).context("Erro ao aguardar leitura"),
+            }
+            // Rearma o interesse de leitura para a próxima espera
+            self.poller
+                .modify(&self.sock, Event::readable(0))
+                .context("Erro ao rearmar o poller")?;
+
+            #[cfg(unix)]
+            let recv_result = if self.dst.is_ipv6() {
+                recv_from_v6_with_hop_limit(&self.sock, &mut buf)
+            } else {
+                self.sock
+                    .recv_from(&mut buf)
+                    .map(|(n, peer)| (n, peer.as_socket().map(|sa| sa.ip()), None))
+            };
+            #[cfg(not(unix))]
+            let recv_result = self
+                .sock
+                .recv_from(&mut buf)
+                .map(|(n, peer)| (n, peer.as_socket().map(|sa| sa.ip()), None));
+
+            let (n, from, hop_limit) = match recv_result {
+                // Se o kernel não informou o remetente (não deveria acontecer em um
+                // socket raw não conectado), caímos de volta em `dst` — mesma postura
+                // adotada pelo caminho IPv4.
+                Ok((n, from, hop_limit)) => (n, from.unwrap_or(self.dst), hop_limit),
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    continue;
+                }
+                Err(e) => return Err(e).context("Erro na leitura"),
+            };
+            // SAFETY: a leitura acima garante que os primeiros `n` bytes do
+            // buffer foram escritos pelo kernel.
+            let data = unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, n) };
+
+            // Sockets raw IPv4 costumam incluir o cabeçalho IP no início (pula o IHL);
+            // sockets raw ICMPv6, não — o kernel já entrega só a mensagem ICMPv6.
+            let start = if self.dst.is_ipv4() && n >= 20 && (data[0] >> 4) == 4 {
+                (data[0] & 0x0F) as usize * 4
+            } else {
+                0
+            };
+
+            if n < start + 8 {
+                continue;
+            }
+
+            // TTL (IPv4): lido do cabeçalho IP que acompanha o pacote raw.
+            // Hop limit (IPv6): lido do ancillary data entregue por `recvmsg`
+            // (ver `recv_from_v6_with_hop_limit`); ausente em plataformas não-unix.
+            let ttl = if start >= 20 { Some(data[8]) } else { hop_limit };
+
+            let icmp = &data[start..n];
+            let icmp_type = icmp[0]; // 0 (v4) / 129 (v6) = Echo Reply
+            let icmp_code = icmp[1]; // 0
+
+            let echo_reply_type = if self.dst.is_ipv4() { 0 } else { 129 };
+
+            if icmp_type == echo_reply_type && icmp_code == 0 {
+                let r_id = u16::from_be_bytes([icmp[4], icmp[5]]);
+                let r_seq = u16::from_be_bytes([icmp[6], icmp[7]]);
+                if r_id != self.ident || r_seq != seq {
+                    continue;
+                }
+
+                // O RTT vem do timestamp ecoado no próprio payload, não do relógio
+                // local. Só é aceita a resposta à sequência do pedido atual; uma
+                // resposta atrasada a um pedido anterior é descartada acima.
+                let echoed = &icmp[8..];
+                let (rtt, corrupted) = if echoed.len() >= MIN_PAYLOAD_SIZE {
+                    let sent_nanos = u64::from_be_bytes(echoed[..8].try_into().unwrap());
+                    let now_nanos = Instant::now().duration_since(self.start).as_nanos() as u64;
+                    let rtt = Duration::from_nanos(now_nanos.saturating_sub(sent_nanos));
+                    let expected = build_payload(self.opts.payload_size, sent_nanos);
+                    (rtt, echoed != expected.as_slice())
+                } else {
+                    (t0.elapsed(), true)
+                };
+
+                return Ok(PingOutcome::Reply {
+                    seq,
+                    rtt,
+                    bytes: n - start,
+                    ttl,
+                    corrupted,
+                });
+            }
+
+            // Não é um Echo Reply: talvez seja um erro ICMP (inacessível, TTL
+            // excedido, ...) embutindo o cabeçalho do nosso Echo Request original.
+            let parsed = if self.dst.is_ipv4() {
+                icmp::parse_error_v4(icmp)
+            } else {
+                icmp::parse_error_v6(icmp)
+            };
+            if let Some((kind, r_id, r_seq)) = parsed {
+                if r_id == self.ident && r_seq == seq {
+                    return Ok(PingOutcome::Error { seq, kind, from });
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Pinger {
+    type Item = Result<PingOutcome>;
+
+    /// Envia o próximo Echo Request e aguarda o desfecho, respeitando `count`
+    /// e `running`. Dorme o restante de `interval` entre um pedido e o
+    /// próximo, descontando o tempo já gasto, para que a cadência não sofra
+    /// drift.
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(limit) = self.opts.count {
+            if self.sent >= limit {
+                return None;
+            }
+        }
+        if !self.opts.running.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let t0 = Instant::now();
+        let outcome = self.ping_once();
+
+        let done = self.opts.count.is_some_and(|limit| self.sent >= limit);
+        if !done && self.opts.running.load(Ordering::SeqCst) {
+            let sleep_for = self.opts.interval.saturating_sub(t0.elapsed());
+            std::thread::sleep(sleep_for);
+        }
+
+        Some(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_payload_embute_o_timestamp_nos_primeiros_8_bytes() {
+        let payload = build_payload(DEFAULT_PAYLOAD_SIZE, 0x0102030405060708);
+        assert_eq!(&payload[..8], &0x0102030405060708u64.to_be_bytes()[..]);
+        assert_eq!(payload.len(), DEFAULT_PAYLOAD_SIZE);
+    }
+
+    #[test]
+    fn build_payload_preenche_o_restante_com_o_padrao_conhecido() {
+        let payload = build_payload(MIN_PAYLOAD_SIZE + 5, 0);
+        assert_eq!(&payload[8..], &PAYLOAD_FILLER[..5]);
+    }
+
+    #[test]
+    fn build_payload_respeita_o_tamanho_minimo() {
+        // Um `size` menor que MIN_PAYLOAD_SIZE não pode cortar o timestamp embutido.
+        let payload = build_payload(0, 42);
+        assert_eq!(payload.len(), MIN_PAYLOAD_SIZE);
+    }
+}